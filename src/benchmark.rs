@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::Write as _;
+use std::time::Duration;
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{spawn_cubes, Args, CubeSpawner, CubesCounter, MyCube, SpawnRng};
+
+/// cubes/sec added to the spawn rate at each sweep step
+const STEP: u32 = 1_000;
+/// sweep stops once FPS drops below this, since lower samples are no longer
+/// representative of real-time rendering performance
+const MIN_FPS: f64 = 20.0;
+/// hard ceiling so a misconfigured sweep can't spawn forever
+const MAX_COUNT: u32 = 200_000;
+const OUTPUT_PATH: &str = "benchmark.csv";
+
+/// drives an automated spawn-rate sweep: every `dwell` seconds it samples FPS at the
+/// current rate, appends a row to `benchmark.csv`, then bumps the spawner's recorded rate
+/// by `STEP` and spawns `STEP` more cubes at once, until the SMA FPS drops below `MIN_FPS`
+/// or `MAX_COUNT` is reached
+#[derive(Resource)]
+pub(crate) struct BenchmarkState {
+    dwell_timer: Timer,
+    csv: File,
+}
+
+impl BenchmarkState {
+    pub(crate) fn new(dwell_seconds: f32) -> Self {
+        let mut csv = File::create(OUTPUT_PATH).expect("failed to create benchmark output file");
+        writeln!(
+            csv,
+            "cube_count,spawn_rate,fps_raw,fps_sma,fps_ema,frame_time_ms"
+        )
+        .expect("failed to write benchmark header");
+
+        Self {
+            dwell_timer: Timer::new(Duration::from_secs_f32(dwell_seconds), TimerMode::Repeating),
+            csv,
+        }
+    }
+}
+
+pub(crate) fn benchmark_system(
+    mut commands: Commands,
+    mut benchmark_state: Option<ResMut<BenchmarkState>>,
+    mut spawner_query: Query<&mut CubeSpawner>,
+    my: Res<MyCube>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut counter: ResMut<CubesCounter>,
+    mut rng: ResMut<SpawnRng>,
+    args: Res<Args>,
+    diagnostics: Res<Diagnostics>,
+    time: Res<Time>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(mut benchmark_state) = benchmark_state.as_mut() else {
+        return;
+    };
+
+    benchmark_state.dwell_timer.tick(time.delta());
+    if !benchmark_state.dwell_timer.just_finished() {
+        return;
+    }
+
+    let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) else {
+        return;
+    };
+    let Some(frame_time) = diagnostics.get(FrameTimeDiagnosticsPlugin::FRAME_TIME) else {
+        return;
+    };
+
+    let fps_raw = fps.value().unwrap_or_default();
+    let fps_sma = fps.average().unwrap_or_default();
+    let fps_ema = fps.smoothed().unwrap_or_default();
+    let frame_time_ms = frame_time.value().unwrap_or_default();
+
+    let mut spawner = spawner_query.single_mut();
+
+    writeln!(
+        benchmark_state.csv,
+        "{},{},{fps_raw:.2},{fps_sma:.2},{fps_ema:.2},{frame_time_ms:.3}",
+        counter.count, spawner.spawning_rate,
+    )
+    .expect("failed to write benchmark row");
+
+    if fps_sma < MIN_FPS || counter.count >= MAX_COUNT {
+        exit.send(AppExit);
+        return;
+    }
+
+    spawner.spawning_rate += STEP;
+    spawn_cubes(
+        &mut commands,
+        &my,
+        &mut materials,
+        &mut counter,
+        &mut rng,
+        args.sprite,
+        args.unique_materials,
+        STEP,
+    );
+}
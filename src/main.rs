@@ -1,57 +1,140 @@
 use std::time::Duration;
 
+use argh::FromArgs;
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
     prelude::*,
+    window::PresentMode,
+    winit::{UpdateMode, WinitSettings},
 };
 use bevy_editor_pls::prelude::*;
-use rand::{distributions::Uniform, prelude::Distribution, thread_rng, Rng};
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, SeedableRng};
+
+use benchmark::BenchmarkState;
+
+mod benchmark;
 
-const INITIAL_SPAWNING_RATE: u32 = 100;
 const SPAWNING_RATE_STEP: u32 = 500;
 
+/// a stress test spawning many entities to measure Bevy's rendering and ECS throughput
+#[derive(FromArgs, Resource)]
+pub(crate) struct Args {
+    /// number of cubes spawned per second by the timer-driven spawner
+    #[argh(option, default = "100")]
+    spawn_rate: u32,
+
+    /// number of cubes spawned immediately at startup
+    #[argh(option, default = "0")]
+    initial_count: u32,
+
+    /// if set, run an automated spawn-rate sweep for this many seconds per step and exit
+    #[argh(option)]
+    benchmark_seconds: Option<f32>,
+
+    /// disable vsync to measure uncapped FPS
+    #[argh(switch)]
+    no_vsync: bool,
+
+    /// seed for the deterministic RNG used to place spawned cubes
+    #[argh(option, default = "0")]
+    seed: u64,
+
+    /// render each cube as a flat 2D sprite instead of a 3D mesh
+    #[argh(switch)]
+    pub(crate) sprite: bool,
+
+    /// give every cube its own randomly-colored material instead of sharing one handle,
+    /// to measure the cost of breaking Bevy's automatic batching; with --sprite, each
+    /// sprite gets a random color instead, as sprites use no material
+    #[argh(switch)]
+    pub(crate) unique_materials: bool,
+}
+
 fn main() {
+    let args: Args = argh::from_env();
+    let present_mode = if args.no_vsync {
+        PresentMode::AutoNoVsync
+    } else {
+        PresentMode::AutoVsync
+    };
+
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                present_mode,
+                ..default()
+            }),
+            ..default()
+        }))
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::Continuous,
+            unfocused_mode: UpdateMode::Continuous,
+        })
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(EditorPlugin)
+        .insert_resource(args)
         .add_startup_system(setup)
         .add_system(input_system)
         .add_system(cube_spawning_system)
+        .add_system(click_spawning_system)
         .add_system(movement_system)
         .add_system(counter_system)
+        .add_system(benchmark::benchmark_system)
         .run();
 }
 
 #[derive(Resource)]
-struct MyCube {
+pub(crate) struct MyCube {
     mesh: Handle<Mesh>,
     material: Handle<StandardMaterial>,
 }
 
 #[derive(Resource)]
-struct CubesCounter {
-    count: u32,
+pub(crate) struct CubesCounter {
+    pub(crate) count: u32,
 }
 
+#[derive(Resource)]
+pub(crate) struct SpawnRng(StdRng);
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
+    args: Res<Args>,
 ) {
     let my_cube = MyCube {
         mesh: meshes.add(shape::Cube::new(1.0).into()),
         material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
     };
 
+    let mut rng = SpawnRng(StdRng::seed_from_u64(args.seed));
+    let mut counter = CubesCounter { count: 0 };
+    spawn_cubes(
+        &mut commands,
+        &my_cube,
+        &mut materials,
+        &mut counter,
+        &mut rng,
+        args.sprite,
+        args.unique_materials,
+        args.initial_count,
+    );
+
     commands.insert_resource(my_cube);
-    commands.insert_resource(CubesCounter { count: 0 });
+    commands.insert_resource(counter);
+    commands.insert_resource(rng);
     commands.spawn(CubeSpawner {
-        spawning_rate: INITIAL_SPAWNING_RATE,
+        spawning_rate: args.spawn_rate,
         timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
+        click_spawn_remainder: 0.0,
     });
 
+    if let Some(benchmark_seconds) = args.benchmark_seconds {
+        commands.insert_resource(BenchmarkState::new(benchmark_seconds));
+    }
+
     let text_section = move |color, value: &str| {
         TextSection::new(
             value,
@@ -63,6 +146,12 @@ fn setup(
         )
     };
 
+    let mode = if args.unique_materials {
+        "unique materials"
+    } else {
+        "shared material"
+    };
+
     commands.spawn((
         TextBundle::from_sections([
             text_section(Color::GREEN, "Cubes Count: "),
@@ -75,6 +164,8 @@ fn setup(
             text_section(Color::CYAN, ""),
             text_section(Color::GREEN, "\nFPS (EMA): "),
             text_section(Color::CYAN, ""),
+            text_section(Color::GREEN, "\nMode: "),
+            text_section(Color::CYAN, mode),
         ])
         .with_style(Style {
             position_type: PositionType::Absolute,
@@ -88,32 +179,47 @@ fn setup(
         StatsText,
     ));
 
-    commands.spawn(Camera3dBundle {
-        transform: Transform::from_xyz(-20.0, 25.0, 50.0).looking_at(Vec3::ZERO, Vec3::Y),
-        ..Default::default()
-    });
+    if args.sprite {
+        commands.spawn(Camera2dBundle::default());
+    } else {
+        commands.spawn(Camera3dBundle {
+            transform: Transform::from_xyz(-20.0, 25.0, 50.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        });
+    }
 }
 
-fn movement_system(mut query: Query<(&GeneratedCube, &mut Transform)>, time: Res<Time>) {
-    for (cube, mut transform) in query.iter_mut() {
-        transform.translation.x += time.delta_seconds() * cube.speed;
+/// downward acceleration applied to every cube each frame, in units/sec^2
+const GRAVITY: f32 = -9.8;
+/// cubes bounce off a cube-shaped region of this half-extent centered on the origin
+const BOUNDS: f32 = 20.0;
+
+fn movement_system(mut query: Query<(&mut GeneratedCube, &mut Transform)>, time: Res<Time>) {
+    let delta = time.delta_seconds();
+
+    for (mut cube, mut transform) in query.iter_mut() {
+        cube.velocity.y += GRAVITY * delta;
+        transform.translation += cube.velocity * delta;
 
-        if transform.translation.x > cube.x_range {
-            transform.translation.x -= cube.x_range * 2.0;
+        for axis in 0..3 {
+            if transform.translation[axis].abs() > BOUNDS {
+                transform.translation[axis] = transform.translation[axis].clamp(-BOUNDS, BOUNDS);
+                cube.velocity[axis] = -cube.velocity[axis];
+            }
         }
     }
 }
 
 #[derive(Component)]
 struct GeneratedCube {
-    speed: f32,
-    x_range: f32,
+    velocity: Vec3,
 }
 
 #[derive(Component)]
-struct CubeSpawner {
-    spawning_rate: u32,
+pub(crate) struct CubeSpawner {
+    pub(crate) spawning_rate: u32,
     timer: Timer,
+    click_spawn_remainder: f32,
 }
 
 fn cube_spawning_system(
@@ -121,34 +227,111 @@ fn cube_spawning_system(
     mut query: Query<&mut CubeSpawner>,
     time: Res<Time>,
     my: Res<MyCube>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
     mut counter: ResMut<CubesCounter>,
+    mut rng: ResMut<SpawnRng>,
+    args: Res<Args>,
+    benchmark_state: Option<Res<BenchmarkState>>,
 ) {
+    // while a benchmark sweep is running it drives the spawn rate itself; letting the
+    // timer-driven spawner run alongside it would corrupt the sampled cube counts.
+    if benchmark_state.is_some() {
+        return;
+    }
+
     for mut spawner in query.iter_mut() {
         spawner.timer.tick(time.delta());
 
         if spawner.timer.finished() {
-            for _ in 0..spawner.spawning_rate {
-                let between = Uniform::from(-10.0..10.0);
-                let mut rng = thread_rng();
-                let x = between.sample(&mut rng);
-                let y = between.sample(&mut rng);
-                let z = between.sample(&mut rng);
-
-                commands.spawn((
-                    PbrBundle {
-                        mesh: my.mesh.clone(),
-                        material: my.material.clone(),
-                        transform: Transform::from_xyz(x, y, z),
-                        ..Default::default()
-                    },
-                    GeneratedCube {
-                        x_range: 20.0,
-                        speed: 10.0,
+            spawn_cubes(
+                &mut commands,
+                &my,
+                &mut materials,
+                &mut counter,
+                &mut rng,
+                args.sprite,
+                args.unique_materials,
+                spawner.spawning_rate,
+            );
+        }
+    }
+}
+
+pub(crate) fn spawn_cubes(
+    commands: &mut Commands,
+    my: &MyCube,
+    materials: &mut Assets<StandardMaterial>,
+    counter: &mut CubesCounter,
+    rng: &mut SpawnRng,
+    sprite: bool,
+    unique_materials: bool,
+    count: u32,
+) {
+    let position = Uniform::from(-10.0..10.0);
+    let velocity = Uniform::from(-5.0..5.0);
+    let color_channel = Uniform::from(0.0..1.0);
+    for _ in 0..count {
+        let x = position.sample(&mut rng.0);
+        let y = position.sample(&mut rng.0);
+        let z = position.sample(&mut rng.0);
+        let transform = Transform::from_xyz(x, y, z);
+
+        let cube = GeneratedCube {
+            velocity: Vec3::new(
+                velocity.sample(&mut rng.0),
+                velocity.sample(&mut rng.0),
+                velocity.sample(&mut rng.0),
+            ),
+        };
+
+        if sprite {
+            let color = if unique_materials {
+                Color::rgb(
+                    color_channel.sample(&mut rng.0),
+                    color_channel.sample(&mut rng.0),
+                    color_channel.sample(&mut rng.0),
+                )
+            } else {
+                Color::rgb(0.8, 0.7, 0.6)
+            };
+
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(1.0, 1.0)),
+                        ..default()
                     },
-                ));
-                counter.count += 1;
-            }
+                    transform,
+                    ..default()
+                },
+                cube,
+            ));
+        } else {
+            let material = if unique_materials {
+                materials.add(
+                    Color::rgb(
+                        color_channel.sample(&mut rng.0),
+                        color_channel.sample(&mut rng.0),
+                        color_channel.sample(&mut rng.0),
+                    )
+                    .into(),
+                )
+            } else {
+                my.material.clone()
+            };
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: my.mesh.clone(),
+                    material,
+                    transform,
+                    ..Default::default()
+                },
+                cube,
+            ));
         }
+        counter.count += 1;
     }
 }
 
@@ -176,6 +359,43 @@ fn input_system(
     }
 }
 
+/// cubes/sec spawned while the left mouse button is held
+const CLICK_SPAWNING_RATE: f32 = 1_000.0;
+
+fn click_spawning_system(
+    mouse_input: Res<Input<MouseButton>>,
+    mut commands: Commands,
+    mut query: Query<&mut CubeSpawner>,
+    my: Res<MyCube>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut counter: ResMut<CubesCounter>,
+    mut rng: ResMut<SpawnRng>,
+    args: Res<Args>,
+    time: Res<Time>,
+    benchmark_state: Option<Res<BenchmarkState>>,
+) {
+    // while a benchmark sweep is running it drives the spawn rate itself; letting the
+    // click spawner run alongside it would corrupt the sampled cube counts.
+    if benchmark_state.is_some() || !mouse_input.pressed(MouseButton::Left) {
+        return;
+    }
+
+    let mut spawner = query.single_mut();
+    let to_spawn = CLICK_SPAWNING_RATE * time.delta_seconds() + spawner.click_spawn_remainder;
+    spawner.click_spawn_remainder = to_spawn.fract();
+
+    spawn_cubes(
+        &mut commands,
+        &my,
+        &mut materials,
+        &mut counter,
+        &mut rng,
+        args.sprite,
+        args.unique_materials,
+        to_spawn as u32,
+    );
+}
+
 #[derive(Component)]
 struct StatsText;
 